@@ -0,0 +1,49 @@
+use std::io;
+
+/// Abstracts the sink a metric is encoded into, so that the same
+/// [`EncodeMetric`] implementation can describe itself to the Prometheus
+/// text format, the protobuf wire format (see [`crate::MetricsEncoder]),
+/// or any other format that implements this trait.
+///
+/// This mirrors the split `serde::Serializer` makes between "how to
+/// describe a value" and "how to write it down".
+pub trait Encoder {
+    /// Encodes the metadata and the value of a counter.
+    fn encode_counter(&mut self, name: &str, value: f64, help: &str) -> io::Result<()>;
+
+    /// Encodes the metadata and the value of a gauge.
+    fn encode_gauge(&mut self, name: &str, value: f64, help: &str) -> io::Result<()>;
+
+    /// Encodes the metadata and the value of a histogram. See
+    /// [`crate::MetricsEncoder::encode_histogram`] for the meaning of
+    /// `buckets` and `sum`.
+    fn encode_histogram(
+        &mut self,
+        name: &str,
+        buckets: &mut dyn Iterator<Item = (f64, f64)>,
+        sum: f64,
+        help: &str,
+    ) -> io::Result<()>;
+
+    /// Encodes one row per `(labels, value)` pair of a counter or gauge
+    /// that varies by label, with `typ` being either `"counter"` or
+    /// `"gauge"`.
+    fn encode_labels(
+        &mut self,
+        typ: &str,
+        name: &str,
+        help: &str,
+        rows: &[(&[(&str, &str)], f64)],
+    ) -> io::Result<()>;
+}
+
+/// Describes how a user-defined type reports itself as a metric, to any
+/// [`Encoder`].
+///
+/// Implement this for your own metric types instead of writing directly
+/// against [`crate::MetricsEncoder`], so that callers can choose whichever
+/// `Encoder` fits their scrape endpoint. Kept object-safe so a registry
+/// can hold `Box<dyn EncodeMetric>`.
+pub trait EncodeMetric {
+    fn encode_metric(&self, encoder: &mut dyn Encoder) -> io::Result<()>;
+}
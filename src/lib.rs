@@ -2,9 +2,14 @@ use std::fmt;
 use std::io;
 use std::iter::once;
 
+mod encoder;
+mod proto;
+
 #[cfg(test)]
 mod tests;
 
+pub use encoder::{EncodeMetric, Encoder};
+
 struct FormattedValue(f64);
 
 impl fmt::Display for FormattedValue {
@@ -27,6 +32,60 @@ impl fmt::Display for FormattedValue {
     }
 }
 
+/// Formats `now_millis` as the timestamp to attach to a text-format
+/// sample: milliseconds for classic Prometheus text, or Unix-epoch seconds
+/// as the [OpenMetrics text format](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md#timestamps)
+/// requires.
+fn sample_timestamp(now_millis: i64, openmetrics: bool) -> String {
+    if openmetrics {
+        (now_millis as f64 / 1000.0).to_string()
+    } else {
+        now_millis.to_string()
+    }
+}
+
+/// An [OpenMetrics exemplar](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md#exemplars):
+/// a reference to a specific observation, e.g. a trace, backing a sample.
+///
+/// Exemplars are only emitted in OpenMetrics mode; see
+/// [MetricsEncoder::new_openmetrics].
+pub struct Exemplar<'a> {
+    pub labels: &'a [(&'a str, &'a str)],
+    pub value: f64,
+    pub timestamp: Option<i64>,
+}
+
+/// A unit of measurement for a metric, following the
+/// [OpenMetrics unit convention](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md#units-and-base-units).
+///
+/// Metrics that carry a unit are expected to have their name end with
+/// `_<unit>`, e.g. a [Unit::Seconds] gauge named `request_duration_seconds`.
+/// See [MetricsEncoder::encode_gauge_with_unit].
+pub enum Unit {
+    Seconds,
+    Bytes,
+    Count,
+    Ratio,
+    Percent,
+    /// A base unit not covered above, given as its canonical lowercase
+    /// name, e.g. `"joules"`.
+    Other(&'static str),
+}
+
+impl Unit {
+    /// Returns the canonical, lowercase base-unit string, e.g. `"seconds"`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Unit::Seconds => "seconds",
+            Unit::Bytes => "bytes",
+            Unit::Count => "count",
+            Unit::Ratio => "ratio",
+            Unit::Percent => "percent",
+            Unit::Other(unit) => unit,
+        }
+    }
+}
+
 /// A helper for encoding metrics that use
 /// [labels](https://prometheus.io/docs/practices/naming/#labels).
 /// See [MetricsEncoder::counter_vec] and [MetricsEncoder::gauge_vec].
@@ -35,7 +94,10 @@ where
     W: io::Write,
 {
     encoder: &'a mut MetricsEncoder<W>,
-    name: &'a str,
+    // Owned, rather than `&'a str`, because in OpenMetrics mode a counter's
+    // exposed name is the caller's name plus a `_total` suffix computed at
+    // `counter_vec` time.
+    name: String,
 }
 
 impl<W: io::Write> LabeledMetricsBuilder<'_, W> {
@@ -48,7 +110,17 @@ impl<W: io::Write> LabeledMetricsBuilder<'_, W> {
     /// https://prometheus.io/docs/concepts/data_model/#metric-names-and-labels.
     pub fn value(self, labels: &[(&str, &str)], value: f64) -> io::Result<Self> {
         self.encoder
-            .encode_value_with_labels(self.name, labels, value)?;
+            .encode_value_with_labels(&self.name, labels, value)?;
+        Ok(self)
+    }
+
+    /// Like [Self::value], but returns an [io::Error] instead of panicking
+    /// when one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], so a bad dynamic label can be skipped instead
+    /// of aborting the whole scrape.
+    pub fn try_value(self, labels: &[(&str, &str)], value: f64) -> io::Result<Self> {
+        self.encoder
+            .try_encode_value_with_labels(&self.name, labels, value)?;
         Ok(self)
     }
 }
@@ -77,87 +149,319 @@ impl<W: io::Write> LabeledHistogramBuilder<'_, W> {
         labels: &[(&str, &str)],
         buckets: impl Iterator<Item = (f64, f64)>,
         sum: f64,
+    ) -> io::Result<Self> {
+        self.histogram_with_exemplars(labels, buckets.map(|(bucket, v)| (bucket, v, None)), sum)
+    }
+
+    /// Like [Self::histogram], but returns an [io::Error] instead of
+    /// panicking when one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], so a bad dynamic label can be skipped instead
+    /// of aborting the whole scrape.
+    pub fn try_histogram(
+        self,
+        labels: &[(&str, &str)],
+        buckets: impl Iterator<Item = (f64, f64)>,
+        sum: f64,
+    ) -> io::Result<Self> {
+        self.try_histogram_with_exemplars(labels, buckets.map(|(bucket, v)| (bucket, v, None)), sum)
+    }
+
+    /// Like [Self::histogram], but attaches an
+    /// [exemplar](Exemplar) to the buckets that have one. Exemplars are
+    /// only rendered when the encoder is in OpenMetrics mode; elsewhere
+    /// they're silently dropped.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if an exemplar's labels and value exceed the
+    /// OpenMetrics 128 UTF-8 character limit. See
+    /// https://prometheus.io/docs/concepts/data_model/#metric-names-and-labels.
+    pub fn histogram_with_exemplars<'e>(
+        self,
+        labels: &[(&str, &str)],
+        buckets: impl Iterator<Item = (f64, f64, Option<Exemplar<'e>>)>,
+        sum: f64,
+    ) -> io::Result<Self> {
+        panic_on_invalid_name(self.try_histogram_with_exemplars(labels, buckets, sum))
+    }
+
+    /// Like [Self::histogram_with_exemplars], but returns an [io::Error]
+    /// instead of panicking when one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], so a bad dynamic label can be skipped instead
+    /// of aborting the whole scrape.
+    pub fn try_histogram_with_exemplars<'e>(
+        self,
+        labels: &[(&str, &str)],
+        buckets: impl Iterator<Item = (f64, f64, Option<Exemplar<'e>>)>,
+        sum: f64,
     ) -> io::Result<Self> {
         for (label, _) in labels.iter() {
-            validate_prometheus_name(label);
+            try_validate_prometheus_name(label)?;
         }
 
+        let now_millis = self.encoder.now_millis;
         let mut total: f64 = 0.0;
         let mut saw_infinity = false;
-        for (bucket, v) in buckets {
-            total += v;
-            if bucket == std::f64::INFINITY {
-                saw_infinity = true;
-                writeln!(
-                    self.encoder.writer,
-                    "{}_bucket{{{}}} {} {}",
-                    self.name,
-                    MetricsEncoder::<W>::encode_labels(labels.iter().chain(once(&("le", "+Inf")))),
-                    total,
-                    self.encoder.now_millis
-                )?;
-            } else {
-                let bucket_str = bucket.to_string();
-                writeln!(
-                    self.encoder.writer,
-                    "{}_bucket{{{}}} {} {}",
-                    self.name,
-                    MetricsEncoder::<W>::encode_labels(
-                        labels.iter().chain(once(&("le", bucket_str.as_str())))
-                    ),
-                    total,
-                    self.encoder.now_millis
-                )?;
+        let mut proto_buckets = Vec::new();
+
+        match &mut self.encoder.output {
+            Output::Text { writer, openmetrics } => {
+                let timestamp = sample_timestamp(now_millis, *openmetrics);
+                for (bucket, v, exemplar) in buckets {
+                    total += v;
+                    let bucket_str = if bucket == f64::INFINITY {
+                        saw_infinity = true;
+                        "+Inf".to_string()
+                    } else {
+                        bucket.to_string()
+                    };
+                    write!(
+                        writer,
+                        "{}_bucket{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter().chain(once(&("le", bucket_str.as_str())))),
+                        total,
+                        timestamp
+                    )?;
+                    if *openmetrics {
+                        if let Some(exemplar) = &exemplar {
+                            write!(writer, "{}", try_render_exemplar(exemplar)?)?;
+                        }
+                    }
+                    writeln!(writer)?;
+                }
+                if !saw_infinity {
+                    writeln!(
+                        writer,
+                        "{}_bucket{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter().chain(once(&("le", "+Inf")))),
+                        total,
+                        timestamp
+                    )?;
+                }
+
+                if labels.is_empty() {
+                    writeln!(writer, "{}_sum {} {}", self.name, FormattedValue(sum), timestamp)?;
+                    writeln!(writer, "{}_count {} {}", self.name, FormattedValue(total), timestamp)?;
+                } else {
+                    writeln!(
+                        writer,
+                        "{}_sum{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter()),
+                        FormattedValue(sum),
+                        timestamp
+                    )?;
+                    writeln!(
+                        writer,
+                        "{}_count{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter()),
+                        FormattedValue(total),
+                        timestamp
+                    )?;
+                }
+            }
+            Output::Protobuf { families, .. } => {
+                for (bucket, v, _exemplar) in buckets {
+                    total += v;
+                    if bucket == f64::INFINITY {
+                        saw_infinity = true;
+                    }
+                    proto_buckets.push(proto::Bucket {
+                        cumulative_count: total as u64,
+                        upper_bound: bucket,
+                    });
+                }
+                if !saw_infinity {
+                    proto_buckets.push(proto::Bucket {
+                        cumulative_count: total as u64,
+                        upper_bound: f64::INFINITY,
+                    });
+                }
+
+                let family = families
+                    .iter_mut()
+                    .rev()
+                    .find(|family| family.name == self.name)
+                    .expect("histogram_vec always pushes a matching family first");
+                family.metrics.push(proto::Metric {
+                    labels: labels
+                        .iter()
+                        .map(|(k, v)| proto::LabelPair {
+                            name: (*k).to_string(),
+                            value: (*v).to_string(),
+                        })
+                        .collect(),
+                    value: proto::MetricValue::Histogram(proto::Histogram {
+                        sample_count: total as u64,
+                        sample_sum: sum,
+                        buckets: proto_buckets,
+                    }),
+                    timestamp_ms: now_millis,
+                });
             }
         }
-        if !saw_infinity {
-            writeln!(
-                self.encoder.writer,
-                "{}_bucket{{{}}} {} {}",
-                self.name,
-                MetricsEncoder::<W>::encode_labels(labels.iter().chain(once(&("le", "+Inf")))),
-                total,
-                self.encoder.now_millis
-            )?;
+
+        Ok(self)
+    }
+}
+
+/// A helper for encoding summaries that use
+/// [labels](https://prometheus.io/docs/practices/naming/#labels).
+/// See [MetricsEncoder::summary_vec].
+pub struct LabeledSummaryBuilder<'a, W>
+where
+    W: io::Write,
+{
+    encoder: &'a mut MetricsEncoder<W>,
+    name: &'a str,
+}
+
+impl<W: io::Write> LabeledSummaryBuilder<'_, W> {
+    /// Encodes one summary observation for the given values of labels: a
+    /// set of precomputed `(quantile, value)` pairs together with the
+    /// total `sum` and `count` of all observations.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if a quantile is not within `[0, 1]`. See
+    /// https://prometheus.io/docs/concepts/data_model/#metric-names-and-labels.
+    pub fn observe(
+        self,
+        labels: &[(&str, &str)],
+        quantiles: &[(f64, f64)],
+        sum: f64,
+        count: f64,
+    ) -> io::Result<Self> {
+        panic_on_invalid_name(self.try_observe(labels, quantiles, sum, count))
+    }
+
+    /// Like [Self::observe], but returns an [io::Error] instead of
+    /// panicking when one of the labels does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], so a bad dynamic label can be skipped instead
+    /// of aborting the whole scrape.
+    ///
+    /// # Panics
+    ///
+    /// This function still panics if a quantile is not within `[0, 1]`,
+    /// since that is a programming error rather than bad runtime input.
+    pub fn try_observe(
+        self,
+        labels: &[(&str, &str)],
+        quantiles: &[(f64, f64)],
+        sum: f64,
+        count: f64,
+    ) -> io::Result<Self> {
+        for (label, _) in labels.iter() {
+            try_validate_prometheus_name(label)?;
+        }
+        for (phi, _) in quantiles.iter() {
+            if !(0.0..=1.0).contains(phi) {
+                panic!("Quantile '{}' is not within [0, 1]", phi);
+            }
         }
 
-        if labels.is_empty() {
-            writeln!(
-                self.encoder.writer,
-                "{}_sum {} {}",
-                self.name,
-                FormattedValue(sum),
-                self.encoder.now_millis
-            )?;
-            writeln!(
-                self.encoder.writer,
-                "{}_count {} {}",
-                self.name,
-                FormattedValue(total),
-                self.encoder.now_millis
-            )?;
-        } else {
-            writeln!(
-                self.encoder.writer,
-                "{}_sum{{{}}} {} {}",
-                self.name,
-                MetricsEncoder::<W>::encode_labels(labels.iter()),
-                FormattedValue(sum),
-                self.encoder.now_millis
-            )?;
-            writeln!(
-                self.encoder.writer,
-                "{}_count{{{}}} {} {}",
-                self.name,
-                MetricsEncoder::<W>::encode_labels(labels.iter()),
-                FormattedValue(total),
-                self.encoder.now_millis
-            )?;
+        let now_millis = self.encoder.now_millis;
+
+        match &mut self.encoder.output {
+            Output::Text { writer, openmetrics } => {
+                let timestamp = sample_timestamp(now_millis, *openmetrics);
+                for (phi, value) in quantiles.iter() {
+                    let phi_str = phi.to_string();
+                    writeln!(
+                        writer,
+                        "{}{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter().chain(once(&("quantile", phi_str.as_str())))),
+                        FormattedValue(*value),
+                        timestamp
+                    )?;
+                }
+
+                if labels.is_empty() {
+                    writeln!(writer, "{}_sum {} {}", self.name, FormattedValue(sum), timestamp)?;
+                    writeln!(writer, "{}_count {} {}", self.name, FormattedValue(count), timestamp)?;
+                } else {
+                    writeln!(
+                        writer,
+                        "{}_sum{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter()),
+                        FormattedValue(sum),
+                        timestamp
+                    )?;
+                    writeln!(
+                        writer,
+                        "{}_count{{{}}} {} {}",
+                        self.name,
+                        format_labels(labels.iter()),
+                        FormattedValue(count),
+                        timestamp
+                    )?;
+                }
+            }
+            Output::Protobuf { families, .. } => {
+                let family = families
+                    .iter_mut()
+                    .rev()
+                    .find(|family| family.name == self.name)
+                    .expect("summary_vec always pushes a matching family first");
+                family.metrics.push(proto::Metric {
+                    labels: labels
+                        .iter()
+                        .map(|(k, v)| proto::LabelPair {
+                            name: (*k).to_string(),
+                            value: (*v).to_string(),
+                        })
+                        .collect(),
+                    value: proto::MetricValue::Summary(proto::Summary {
+                        sample_count: count as u64,
+                        sample_sum: sum,
+                        quantiles: quantiles
+                            .iter()
+                            .map(|(phi, value)| proto::Quantile {
+                                quantile: *phi,
+                                value: *value,
+                            })
+                            .collect(),
+                    }),
+                    timestamp_ms: now_millis,
+                });
+            }
         }
 
         Ok(self)
     }
 }
+
+/// Where a [`MetricsEncoder`] writes its encoded metrics.
+enum Output<W: io::Write> {
+    /// Text written directly to `writer` as it is produced: either the
+    /// classic Prometheus exposition format, or, when `openmetrics` is
+    /// set, the stricter OpenMetrics text format.
+    Text { writer: W, openmetrics: bool },
+    /// The `io.prometheus.client` protobuf wire format. Each metric family
+    /// has to be emitted as a single length-delimited message, so families
+    /// are accumulated here and flushed to `writer` by [`MetricsEncoder::into_inner`].
+    Protobuf {
+        writer: W,
+        families: Vec<proto::MetricFamily>,
+    },
+}
+
+fn proto_metric_type(typ: &str) -> proto::MetricType {
+    match typ {
+        "counter" => proto::MetricType::Counter,
+        "histogram" => proto::MetricType::Histogram,
+        "summary" => proto::MetricType::Summary,
+        _ => proto::MetricType::Gauge,
+    }
+}
+
 /// `MetricsEncoder` provides methods to encode metrics in a text format
 /// that can be understood by Prometheus.
 ///
@@ -167,28 +471,140 @@ impl<W: io::Write> LabeledHistogramBuilder<'_, W> {
 /// See [Exposition Formats][1] for an informal specification of the text
 /// format.
 ///
+/// By default metrics are encoded as Prometheus text. Use
+/// [MetricsEncoder::new_protobuf] to encode the `io.prometheus.client`
+/// protobuf wire format instead, e.g. for the
+/// `application/vnd.google.protobuf` content-negotiated scrape endpoint, or
+/// [MetricsEncoder::new_openmetrics] to encode the stricter OpenMetrics
+/// text format.
+///
 /// [1]: https://github.com/prometheus/docs/blob/master/content/docs/instrumenting/exposition_formats.md
 pub struct MetricsEncoder<W: io::Write> {
-    writer: W,
+    output: Output<W>,
     now_millis: i64,
 }
 
 impl<W: io::Write> MetricsEncoder<W> {
-    /// Constructs a new encoder dumping metrics with the given timestamp into
-    /// the specified writer.
+    /// Constructs a new encoder dumping metrics as Prometheus text with the
+    /// given timestamp into the specified writer.
     pub fn new(writer: W, now_millis: i64) -> Self {
-        Self { writer, now_millis }
+        Self {
+            output: Output::Text {
+                writer,
+                openmetrics: false,
+            },
+            now_millis,
+        }
+    }
+
+    /// Constructs a new encoder dumping metrics as
+    /// [OpenMetrics](https://openmetrics.io/) text with the given timestamp
+    /// into the specified writer.
+    ///
+    /// This differs from [MetricsEncoder::new] in that counters are
+    /// exposed with a `_total` name suffix, `# UNIT` metadata is emitted
+    /// where a unit was supplied, and [MetricsEncoder::into_inner] appends
+    /// the mandatory trailing `# EOF` marker.
+    pub fn new_openmetrics(writer: W, now_millis: i64) -> Self {
+        Self {
+            output: Output::Text {
+                writer,
+                openmetrics: true,
+            },
+            now_millis,
+        }
+    }
+
+    /// Constructs a new encoder dumping metrics as the `io.prometheus.client`
+    /// protobuf wire format with the given timestamp into the specified
+    /// writer.
+    pub fn new_protobuf(writer: W, now_millis: i64) -> Self {
+        Self {
+            output: Output::Protobuf {
+                writer,
+                families: Vec::new(),
+            },
+            now_millis,
+        }
+    }
+
+    /// Returns the name under which a counter should be exposed, suffixing
+    /// it with `_total` as OpenMetrics requires when in OpenMetrics mode.
+    ///
+    /// Returns an [InvalidNameError] if `name` already ends in `_total`
+    /// while in OpenMetrics mode. Callers on the panicking API wrap this
+    /// with [panic_on_invalid_name]; callers on the `try_*` API propagate
+    /// it as-is.
+    fn try_counter_name(&self, name: &str) -> io::Result<String> {
+        match self.output {
+            Output::Text {
+                openmetrics: true, ..
+            } => {
+                if name.ends_with("_total") {
+                    return Err(invalid_name_error(format!(
+                        "Counter name '{}' must not already end in '_total' in OpenMetrics mode",
+                        name
+                    )));
+                }
+                Ok(format!("{}_total", name))
+            }
+            _ => Ok(name.to_string()),
+        }
     }
 
     /// Returns the internal buffer that was used to record the
-    /// metrics.
-    pub fn into_inner(self) -> W {
-        self.writer
+    /// metrics, flushing any buffered protobuf messages and/or the
+    /// OpenMetrics `# EOF` marker first.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.output {
+            Output::Text {
+                mut writer,
+                openmetrics,
+            } => {
+                if openmetrics {
+                    writeln!(writer, "# EOF")?;
+                }
+                Ok(writer)
+            }
+            Output::Protobuf {
+                mut writer,
+                families,
+            } => {
+                for family in &families {
+                    writer.write_all(&family.encode_length_delimited())?;
+                }
+                Ok(writer)
+            }
+        }
     }
 
-    fn encode_header(&mut self, name: &str, help: &str, typ: &str) -> io::Result<()> {
-        writeln!(self.writer, "# HELP {} {}", name, help)?;
-        writeln!(self.writer, "# TYPE {} {}", name, typ)
+    fn encode_header(
+        &mut self,
+        name: &str,
+        help: &str,
+        typ: &str,
+        unit: Option<&str>,
+    ) -> io::Result<()> {
+        match &mut self.output {
+            Output::Text { writer, openmetrics } => {
+                if *openmetrics {
+                    if let Some(unit) = unit {
+                        writeln!(writer, "# UNIT {} {}", name, unit)?;
+                    }
+                }
+                writeln!(writer, "# HELP {} {}", name, help)?;
+                writeln!(writer, "# TYPE {} {}", name, typ)
+            }
+            Output::Protobuf { families, .. } => {
+                families.push(proto::MetricFamily {
+                    name: name.to_string(),
+                    help: help.to_string(),
+                    typ: proto_metric_type(typ),
+                    metrics: Vec::new(),
+                });
+                Ok(())
+            }
+        }
     }
 
     /// Encodes the metadata and the value of a histogram.
@@ -211,19 +627,120 @@ impl<W: io::Write> MetricsEncoder<W> {
         Ok(())
     }
 
+    /// Like [Self::encode_histogram], but returns an [io::Error] instead of
+    /// panicking when `name` or a label does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], so a bad name sourced from runtime
+    /// configuration can be skipped instead of aborting the whole scrape.
+    pub fn try_encode_histogram(
+        &mut self,
+        name: &str,
+        buckets: impl Iterator<Item = (f64, f64)>,
+        sum: f64,
+        help: &str,
+    ) -> io::Result<()> {
+        self.try_histogram_vec(name, help)?
+            .try_histogram(&[], buckets, sum)?;
+        Ok(())
+    }
+
+    /// Like [Self::encode_histogram], but records the metric's [Unit] for
+    /// emission (`# UNIT` in OpenMetrics mode).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if it does not end with `_<unit>`.
+    pub fn encode_histogram_with_unit(
+        &mut self,
+        name: &str,
+        buckets: impl Iterator<Item = (f64, f64)>,
+        sum: f64,
+        unit: Unit,
+        help: &str,
+    ) -> io::Result<()> {
+        self.histogram_vec_with_unit(name, unit, help)?
+            .histogram(&[], buckets, sum)?;
+        Ok(())
+    }
+
     pub fn histogram_vec<'a>(
         &'a mut self,
         name: &'a str,
         help: &'a str,
+    ) -> io::Result<LabeledHistogramBuilder<'a, W>> {
+        panic_on_invalid_name(self.try_histogram_vec(name, help))
+    }
+
+    /// Like [Self::histogram_vec], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_histogram_vec<'a>(
+        &'a mut self,
+        name: &'a str,
+        help: &'a str,
+    ) -> io::Result<LabeledHistogramBuilder<'a, W>> {
+        try_validate_prometheus_name(name)?;
+        self.encode_header(name, help, "histogram", None)?;
+        Ok(LabeledHistogramBuilder {
+            encoder: self,
+            name,
+        })
+    }
+
+    /// Like [Self::histogram_vec], but records the metric's [Unit] for
+    /// emission (`# UNIT` in OpenMetrics mode).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if it does not end with `_<unit>`.
+    pub fn histogram_vec_with_unit<'a>(
+        &'a mut self,
+        name: &'a str,
+        unit: Unit,
+        help: &'a str,
     ) -> io::Result<LabeledHistogramBuilder<'a, W>> {
         validate_prometheus_name(name);
-        self.encode_header(name, help, "histogram")?;
+        validate_unit_suffix(name, &unit);
+        self.encode_header(name, help, "histogram", Some(unit.as_str()))?;
         Ok(LabeledHistogramBuilder {
             encoder: self,
             name,
         })
     }
 
+    /// Starts encoding of a summary that uses
+    /// [labels](https://prometheus.io/docs/practices/naming/#labels).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern [a-zA-Z_][a-zA-Z0-9_].
+    pub fn summary_vec<'a>(
+        &'a mut self,
+        name: &'a str,
+        help: &'a str,
+    ) -> io::Result<LabeledSummaryBuilder<'a, W>> {
+        panic_on_invalid_name(self.try_summary_vec(name, help))
+    }
+
+    /// Like [Self::summary_vec], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_summary_vec<'a>(
+        &'a mut self,
+        name: &'a str,
+        help: &'a str,
+    ) -> io::Result<LabeledSummaryBuilder<'a, W>> {
+        try_validate_prometheus_name(name)?;
+        self.encode_header(name, help, "summary", None)?;
+        Ok(LabeledSummaryBuilder {
+            encoder: self,
+            name,
+        })
+    }
+
     pub fn encode_single_value(
         &mut self,
         typ: &str,
@@ -231,15 +748,82 @@ impl<W: io::Write> MetricsEncoder<W> {
         value: f64,
         help: &str,
     ) -> io::Result<()> {
-        validate_prometheus_name(name);
-        self.encode_header(name, help, typ)?;
-        writeln!(
-            self.writer,
-            "{} {} {}",
-            name,
-            FormattedValue(value),
-            self.now_millis
-        )
+        panic_on_invalid_name(self.encode_single_value_full(typ, name, value, help, None, None))
+    }
+
+    fn encode_single_value_with_exemplar(
+        &mut self,
+        typ: &str,
+        name: &str,
+        value: f64,
+        help: &str,
+        exemplar: Option<Exemplar>,
+    ) -> io::Result<()> {
+        panic_on_invalid_name(self.encode_single_value_full(typ, name, value, help, None, exemplar))
+    }
+
+    fn encode_single_value_with_unit(
+        &mut self,
+        typ: &str,
+        name: &str,
+        value: f64,
+        help: &str,
+        unit: &Unit,
+    ) -> io::Result<()> {
+        validate_unit_suffix(name, unit);
+        panic_on_invalid_name(self.encode_single_value_full(typ, name, value, help, Some(unit), None))
+    }
+
+    fn encode_single_value_full(
+        &mut self,
+        typ: &str,
+        name: &str,
+        value: f64,
+        help: &str,
+        unit: Option<&Unit>,
+        exemplar: Option<Exemplar>,
+    ) -> io::Result<()> {
+        try_validate_prometheus_name(name)?;
+        let exposed_name = if typ == "counter" {
+            self.try_counter_name(name)?
+        } else {
+            name.to_string()
+        };
+        self.encode_header(name, help, typ, unit.map(Unit::as_str))?;
+        let now_millis = self.now_millis;
+        match &mut self.output {
+            Output::Text { writer, openmetrics } => {
+                write!(
+                    writer,
+                    "{} {} {}",
+                    exposed_name,
+                    FormattedValue(value),
+                    sample_timestamp(now_millis, *openmetrics)
+                )?;
+                if *openmetrics {
+                    if let Some(exemplar) = &exemplar {
+                        write!(writer, "{}", try_render_exemplar(exemplar)?)?;
+                    }
+                }
+                writeln!(writer)
+            }
+            Output::Protobuf { families, .. } => {
+                let family = families
+                    .last_mut()
+                    .expect("encode_header just pushed a family");
+                let metric_value = if typ == "counter" {
+                    proto::MetricValue::Counter(value)
+                } else {
+                    proto::MetricValue::Gauge(value)
+                };
+                family.metrics.push(proto::Metric {
+                    labels: Vec::new(),
+                    value: metric_value,
+                    timestamp_ms: now_millis,
+                });
+                Ok(())
+            }
+        }
     }
 
     /// Encodes the metadata and the value of a counter.
@@ -251,6 +835,33 @@ impl<W: io::Write> MetricsEncoder<W> {
         self.encode_single_value("counter", name, value, help)
     }
 
+    /// Like [Self::encode_counter], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_encode_counter(&mut self, name: &str, value: f64, help: &str) -> io::Result<()> {
+        self.encode_single_value_full("counter", name, value, help, None, None)
+    }
+
+    /// Like [Self::encode_counter], but attaches an [exemplar](Exemplar)
+    /// to the observation. Exemplars are only rendered when the encoder
+    /// is in OpenMetrics mode; elsewhere they're silently dropped.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if the exemplar's labels and value exceed
+    /// the OpenMetrics 128 UTF-8 character limit.
+    pub fn encode_counter_with_exemplar(
+        &mut self,
+        name: &str,
+        value: f64,
+        help: &str,
+        exemplar: Exemplar,
+    ) -> io::Result<()> {
+        self.encode_single_value_with_exemplar("counter", name, value, help, Some(exemplar))
+    }
+
     /// Encodes the metadata and the value of a gauge.
     ///
     /// # Panics
@@ -260,6 +871,48 @@ impl<W: io::Write> MetricsEncoder<W> {
         self.encode_single_value("gauge", name, value, help)
     }
 
+    /// Like [Self::encode_gauge], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_encode_gauge(&mut self, name: &str, value: f64, help: &str) -> io::Result<()> {
+        self.encode_single_value_full("gauge", name, value, help, None, None)
+    }
+
+    /// Like [Self::encode_counter], but records the metric's [Unit] for
+    /// emission (`# UNIT` in OpenMetrics mode).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if it does not end with `_<unit>`.
+    pub fn encode_counter_with_unit(
+        &mut self,
+        name: &str,
+        value: f64,
+        unit: Unit,
+        help: &str,
+    ) -> io::Result<()> {
+        self.encode_single_value_with_unit("counter", name, value, help, &unit)
+    }
+
+    /// Like [Self::encode_gauge], but records the metric's [Unit] for
+    /// emission (`# UNIT` in OpenMetrics mode).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `name` argument does not match pattern
+    /// [a-zA-Z_][a-zA-Z0-9_], or if it does not end with `_<unit>`.
+    pub fn encode_gauge_with_unit(
+        &mut self,
+        name: &str,
+        value: f64,
+        unit: Unit,
+        help: &str,
+    ) -> io::Result<()> {
+        self.encode_single_value_with_unit("gauge", name, value, help, &unit)
+    }
+
     /// Starts encoding of a counter that uses
     /// [labels](https://prometheus.io/docs/practices/naming/#labels).
     ///
@@ -271,11 +924,24 @@ impl<W: io::Write> MetricsEncoder<W> {
         name: &'a str,
         help: &'a str,
     ) -> io::Result<LabeledMetricsBuilder<'a, W>> {
-        validate_prometheus_name(name);
-        self.encode_header(name, help, "counter")?;
+        panic_on_invalid_name(self.try_counter_vec(name, help))
+    }
+
+    /// Like [Self::counter_vec], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_counter_vec<'a>(
+        &'a mut self,
+        name: &'a str,
+        help: &'a str,
+    ) -> io::Result<LabeledMetricsBuilder<'a, W>> {
+        try_validate_prometheus_name(name)?;
+        let exposed_name = self.try_counter_name(name)?;
+        self.encode_header(name, help, "counter", None)?;
         Ok(LabeledMetricsBuilder {
             encoder: self,
-            name,
+            name: exposed_name,
         })
     }
 
@@ -290,68 +956,210 @@ impl<W: io::Write> MetricsEncoder<W> {
         name: &'a str,
         help: &'a str,
     ) -> io::Result<LabeledMetricsBuilder<'a, W>> {
-        validate_prometheus_name(name);
-        self.encode_header(name, help, "gauge")?;
+        panic_on_invalid_name(self.try_gauge_vec(name, help))
+    }
+
+    /// Like [Self::gauge_vec], but returns an [io::Error] instead of
+    /// panicking when `name` does not match pattern [a-zA-Z_][a-zA-Z0-9_],
+    /// so a bad name sourced from runtime configuration can be skipped
+    /// instead of aborting the whole scrape.
+    pub fn try_gauge_vec<'a>(
+        &'a mut self,
+        name: &'a str,
+        help: &'a str,
+    ) -> io::Result<LabeledMetricsBuilder<'a, W>> {
+        try_validate_prometheus_name(name)?;
+        self.encode_header(name, help, "gauge", None)?;
         Ok(LabeledMetricsBuilder {
             encoder: self,
-            name,
+            name: name.to_string(),
         })
     }
 
-    fn encode_labels<'a>(labels: impl Iterator<Item = &'a (&'a str, &'a str)>) -> String {
-        let mut buf = String::new();
-        for (i, (k, v)) in labels.enumerate() {
-            validate_prometheus_name(k);
-            if i > 0 {
-                buf.push(',')
-            }
-            buf.push_str(k);
-            buf.push('=');
-            buf.push('"');
-            for c in v.chars() {
-                match c {
-                    '\\' => {
-                        buf.push('\\');
-                        buf.push('\\');
-                    }
-                    '\n' => {
-                        buf.push('\\');
-                        buf.push('n');
-                    }
-                    '"' => {
-                        buf.push('\\');
-                        buf.push('"');
-                    }
-                    _ => buf.push(c),
-                }
-            }
-            buf.push('"');
-        }
-        buf
+    fn encode_value_with_labels(
+        &mut self,
+        name: &str,
+        label_values: &[(&str, &str)],
+        value: f64,
+    ) -> io::Result<()> {
+        panic_on_invalid_name(self.try_encode_value_with_labels(name, label_values, value))
     }
 
-    fn encode_value_with_labels(
+    fn try_encode_value_with_labels(
         &mut self,
         name: &str,
         label_values: &[(&str, &str)],
         value: f64,
     ) -> io::Result<()> {
-        writeln!(
-            self.writer,
-            "{}{{{}}} {} {}",
-            name,
-            Self::encode_labels(label_values.iter()),
-            FormattedValue(value),
-            self.now_millis
-        )
+        let now_millis = self.now_millis;
+        match &mut self.output {
+            Output::Text { writer, openmetrics } => writeln!(
+                writer,
+                "{}{{{}}} {} {}",
+                name,
+                try_format_labels(label_values.iter())?,
+                FormattedValue(value),
+                sample_timestamp(now_millis, *openmetrics)
+            ),
+            Output::Protobuf { families, .. } => {
+                let family = families
+                    .iter_mut()
+                    .rev()
+                    .find(|family| family.name == name)
+                    .expect("counter_vec/gauge_vec always pushes a matching family first");
+                for (k, _) in label_values.iter() {
+                    try_validate_prometheus_name(k)?;
+                }
+                let metric_value = match family.typ {
+                    proto::MetricType::Counter => proto::MetricValue::Counter(value),
+                    _ => proto::MetricValue::Gauge(value),
+                };
+                family.metrics.push(proto::Metric {
+                    labels: label_values
+                        .iter()
+                        .map(|(k, v)| proto::LabelPair {
+                            name: (*k).to_string(),
+                            value: (*v).to_string(),
+                        })
+                        .collect(),
+                    value: metric_value,
+                    timestamp_ms: now_millis,
+                });
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Renders a set of labels as `k1="v1",k2="v2"`, escaping values the way
+/// Prometheus expects. Shared by every `Encoder` implementation that needs
+/// to format labels for a text-like output.
+///
+/// # Panics
+///
+/// This function panics if one of the labels does not match pattern
+/// [a-zA-Z_][a-zA-Z0-9_].
+fn format_labels<'a>(labels: impl Iterator<Item = &'a (&'a str, &'a str)>) -> String {
+    try_format_labels(labels).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like [format_labels], but returns an [io::Error] instead of panicking
+/// when a label key is not a valid Prometheus name.
+fn try_format_labels<'a>(
+    labels: impl Iterator<Item = &'a (&'a str, &'a str)>,
+) -> io::Result<String> {
+    let mut buf = String::new();
+    for (i, (k, v)) in labels.enumerate() {
+        try_validate_prometheus_name(k)?;
+        if i > 0 {
+            buf.push(',')
+        }
+        buf.push_str(k);
+        buf.push('=');
+        buf.push('"');
+        for c in v.chars() {
+            match c {
+                '\\' => {
+                    buf.push('\\');
+                    buf.push('\\');
+                }
+                '\n' => {
+                    buf.push('\\');
+                    buf.push('n');
+                }
+                '"' => {
+                    buf.push('\\');
+                    buf.push('"');
+                }
+                _ => buf.push(c),
+            }
+        }
+        buf.push('"');
+    }
+    Ok(buf)
+}
+
+/// Renders an exemplar as ` # {k="v",...} value [timestamp]`, ready to be
+/// appended after a sample's value and timestamp.
+///
+/// Returns an [InvalidNameError] if one of the exemplar's labels does not
+/// match pattern [a-zA-Z_][a-zA-Z0-9_], or if its labels and value exceed
+/// the OpenMetrics 128 UTF-8 character limit. Callers on the panicking API
+/// wrap this with [panic_on_invalid_name]; callers on the `try_*` API
+/// propagate it as-is.
+fn try_render_exemplar(exemplar: &Exemplar) -> io::Result<String> {
+    let labels = try_format_labels(exemplar.labels.iter())?;
+    let value = FormattedValue(exemplar.value).to_string();
+    // The 128 character limit covers the label names and values and the
+    // exemplar's own value, not the `k="v"` formatting punctuation
+    // `format_labels` adds around them.
+    let content_len: usize = exemplar
+        .labels
+        .iter()
+        .map(|(k, v)| k.chars().count() + v.chars().count())
+        .sum::<usize>()
+        + value.chars().count();
+    if content_len > 128 {
+        return Err(invalid_name_error(format!(
+            "Exemplar labels and value '{{{}}} {}' exceed the OpenMetrics 128 UTF-8 character limit",
+            labels, value
+        )));
     }
+    Ok(match exemplar.timestamp {
+        Some(timestamp) => format!(" # {{{}}} {} {}", labels, value, timestamp),
+        None => format!(" # {{{}}} {}", labels, value),
+    })
+}
+
+/// Panics if `name` does not end with the unit suffix Prometheus naming
+/// best practice expects for a metric carrying `unit`, e.g. `_seconds`.
+/// See https://prometheus.io/docs/practices/naming/#base-units.
+fn validate_unit_suffix(name: &str, unit: &Unit) {
+    let suffix = format!("_{}", unit.as_str());
+    if !name.ends_with(&suffix) {
+        panic!(
+            "Metric name '{}' does not end with the unit suffix '{}'",
+            name, suffix
+        );
+    }
+}
+
+/// Marks an [io::Error] as having been raised by name/label validation
+/// (see [try_validate_prometheus_name], [MetricsEncoder::try_counter_name])
+/// rather than by an actual I/O failure, even though both can be reported
+/// with [io::ErrorKind::InvalidInput]. [panic_on_invalid_name] downcasts to
+/// this type to tell the two apart, so a writer that genuinely returns
+/// `InvalidInput` isn't mistaken for a bad metric name.
+#[derive(Debug)]
+struct InvalidNameError(String);
+
+impl fmt::Display for InvalidNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidNameError {}
+
+fn invalid_name_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, InvalidNameError(message))
 }
 
 /// Panics if the specified string is not a valid Prometheus metric/label name.
 /// See https://prometheus.io/docs/concepts/data_model/#metric-names-and-labels.
 fn validate_prometheus_name(name: &str) {
+    if let Err(err) = try_validate_prometheus_name(name) {
+        panic!("{}", err);
+    }
+}
+
+/// Like [validate_prometheus_name], but returns an [io::Error] describing
+/// the problem instead of panicking, for callers that take metric/label
+/// names from runtime configuration and would rather skip a malformed
+/// series than abort the whole scrape.
+fn try_validate_prometheus_name(name: &str) -> io::Result<()> {
     if name.is_empty() {
-        panic!("Empty names are not allowed");
+        return Err(invalid_name_error("Empty names are not allowed".to_string()));
     }
     let bytes = name.as_bytes();
     if (!bytes[0].is_ascii_alphabetic() && bytes[0] != b'_')
@@ -359,9 +1167,67 @@ fn validate_prometheus_name(name: &str) {
             .iter()
             .all(|c| c.is_ascii_alphanumeric() || *c == b'_')
     {
-        panic!(
+        return Err(invalid_name_error(format!(
             "Name '{}' does not match pattern [a-zA-Z_][a-zA-Z0-9_]",
             name
-        );
+        )));
+    }
+    Ok(())
+}
+
+/// Panics if `result` failed because of an invalid metric/label name, i.e.
+/// propagates genuine I/O errors but turns the [InvalidNameError] errors
+/// raised by [try_validate_prometheus_name]/[MetricsEncoder::try_counter_name]
+/// back into a panic. Used to implement the panicking methods on top of
+/// their fallible `try_*` counterpart without duplicating the encoding logic.
+fn panic_on_invalid_name<T>(result: io::Result<T>) -> io::Result<T> {
+    match result {
+        Err(err) => {
+            if err
+                .get_ref()
+                .is_some_and(|inner| inner.is::<InvalidNameError>())
+            {
+                panic!("{}", err);
+            }
+            Err(err)
+        }
+        ok => ok,
+    }
+}
+
+impl<W: io::Write> Encoder for MetricsEncoder<W> {
+    fn encode_counter(&mut self, name: &str, value: f64, help: &str) -> io::Result<()> {
+        MetricsEncoder::encode_counter(self, name, value, help)
+    }
+
+    fn encode_gauge(&mut self, name: &str, value: f64, help: &str) -> io::Result<()> {
+        MetricsEncoder::encode_gauge(self, name, value, help)
+    }
+
+    fn encode_histogram(
+        &mut self,
+        name: &str,
+        buckets: &mut dyn Iterator<Item = (f64, f64)>,
+        sum: f64,
+        help: &str,
+    ) -> io::Result<()> {
+        MetricsEncoder::encode_histogram(self, name, buckets, sum, help)
+    }
+
+    fn encode_labels(
+        &mut self,
+        typ: &str,
+        name: &str,
+        help: &str,
+        rows: &[(&[(&str, &str)], f64)],
+    ) -> io::Result<()> {
+        let mut builder = match typ {
+            "counter" => self.counter_vec(name, help)?,
+            _ => self.gauge_vec(name, help)?,
+        };
+        for (labels, value) in rows {
+            builder = builder.value(labels, *value)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,229 @@
+//! A minimal protobuf wire-format writer for the `io.prometheus.client`
+//! `MetricFamily` message schema, used by [`crate::MetricsEncoder`] when
+//! constructed in protobuf mode.
+//!
+//! This intentionally only implements the handful of message shapes the
+//! encoder needs (no external protobuf dependency is pulled in): varints,
+//! length-delimited fields, and the `MetricFamily`/`Metric`/`Counter`/
+//! `Gauge`/`Histogram` messages themselves.
+
+/// Appends `value` to `buf` as a base-128 varint.
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_tag(buf: &mut Vec<u8>, field_number: u32, wire_type: u8) {
+    write_varint(buf, ((field_number as u64) << 3) | wire_type as u64);
+}
+
+fn write_string_field(buf: &mut Vec<u8>, field_number: u32, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    write_tag(buf, field_number, 2);
+    write_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn write_double_field(buf: &mut Vec<u8>, field_number: u32, value: f64) {
+    write_tag(buf, field_number, 1);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field_number: u32, value: u64) {
+    write_tag(buf, field_number, 0);
+    write_varint(buf, value);
+}
+
+fn write_message_field(buf: &mut Vec<u8>, field_number: u32, message: &[u8]) {
+    write_tag(buf, field_number, 2);
+    write_varint(buf, message.len() as u64);
+    buf.extend_from_slice(message);
+}
+
+/// A `name = "value"` label pair, as attached to a [`Metric`].
+pub(crate) struct LabelPair {
+    pub name: String,
+    pub value: String,
+}
+
+impl LabelPair {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_string_field(&mut buf, 1, &self.name);
+        write_string_field(&mut buf, 2, &self.value);
+        buf
+    }
+}
+
+/// One bucket of a [`Histogram`]: the cumulative count of observations
+/// less than or equal to `upper_bound`.
+pub(crate) struct Bucket {
+    pub cumulative_count: u64,
+    pub upper_bound: f64,
+}
+
+impl Bucket {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_varint_field(&mut buf, 1, self.cumulative_count);
+        write_double_field(&mut buf, 2, self.upper_bound);
+        buf
+    }
+}
+
+pub(crate) struct Histogram {
+    pub sample_count: u64,
+    pub sample_sum: f64,
+    pub buckets: Vec<Bucket>,
+}
+
+impl Histogram {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_varint_field(&mut buf, 1, self.sample_count);
+        write_double_field(&mut buf, 2, self.sample_sum);
+        for bucket in &self.buckets {
+            write_message_field(&mut buf, 3, &bucket.encode());
+        }
+        buf
+    }
+}
+
+/// One `(quantile, value)` pair of a [`Summary`].
+pub(crate) struct Quantile {
+    pub quantile: f64,
+    pub value: f64,
+}
+
+impl Quantile {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_double_field(&mut buf, 1, self.quantile);
+        write_double_field(&mut buf, 2, self.value);
+        buf
+    }
+}
+
+pub(crate) struct Summary {
+    pub sample_count: u64,
+    pub sample_sum: f64,
+    pub quantiles: Vec<Quantile>,
+}
+
+impl Summary {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_varint_field(&mut buf, 1, self.sample_count);
+        write_double_field(&mut buf, 2, self.sample_sum);
+        for quantile in &self.quantiles {
+            write_message_field(&mut buf, 3, &quantile.encode());
+        }
+        buf
+    }
+}
+
+/// The value carried by a single sample: a plain counter/gauge value or a
+/// full histogram/summary.
+pub(crate) enum MetricValue {
+    Counter(f64),
+    Gauge(f64),
+    Histogram(Histogram),
+    Summary(Summary),
+}
+
+/// A single labeled observation within a [`MetricFamily`].
+pub(crate) struct Metric {
+    pub labels: Vec<LabelPair>,
+    pub value: MetricValue,
+    pub timestamp_ms: i64,
+}
+
+impl Metric {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for label in &self.labels {
+            write_message_field(&mut buf, 1, &label.encode());
+        }
+        match &self.value {
+            MetricValue::Gauge(value) => {
+                let mut inner = Vec::new();
+                write_double_field(&mut inner, 1, *value);
+                write_message_field(&mut buf, 2, &inner);
+            }
+            MetricValue::Counter(value) => {
+                let mut inner = Vec::new();
+                write_double_field(&mut inner, 1, *value);
+                write_message_field(&mut buf, 3, &inner);
+            }
+            MetricValue::Histogram(histogram) => {
+                write_message_field(&mut buf, 7, &histogram.encode());
+            }
+            MetricValue::Summary(summary) => {
+                write_message_field(&mut buf, 4, &summary.encode());
+            }
+        }
+        write_varint_field(&mut buf, 6, self.timestamp_ms as u64);
+        buf
+    }
+}
+
+pub(crate) enum MetricType {
+    Counter,
+    Gauge,
+    Summary,
+    Histogram,
+}
+
+impl MetricType {
+    fn as_i32(&self) -> i32 {
+        match self {
+            MetricType::Counter => 0,
+            MetricType::Gauge => 1,
+            MetricType::Summary => 2,
+            MetricType::Histogram => 4,
+        }
+    }
+}
+
+/// One named, typed metric family: the `# HELP`/`# TYPE` metadata plus
+/// every sample recorded for that name.
+pub(crate) struct MetricFamily {
+    pub name: String,
+    pub help: String,
+    pub typ: MetricType,
+    pub metrics: Vec<Metric>,
+}
+
+impl MetricFamily {
+    /// Encodes this family as a standalone `MetricFamily` message.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_string_field(&mut buf, 1, &self.name);
+        write_string_field(&mut buf, 2, &self.help);
+        write_varint_field(&mut buf, 3, self.typ.as_i32() as u64);
+        for metric in &self.metrics {
+            write_message_field(&mut buf, 4, &metric.encode());
+        }
+        buf
+    }
+
+    /// Encodes this family prefixed with its own length, as is expected
+    /// when concatenating several `MetricFamily` messages into a single
+    /// `application/vnd.google.protobuf` stream.
+    pub fn encode_length_delimited(&self) -> Vec<u8> {
+        let message = self.encode();
+        let mut buf = Vec::new();
+        write_varint(&mut buf, message.len() as u64);
+        buf.extend_from_slice(&message);
+        buf
+    }
+}
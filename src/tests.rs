@@ -0,0 +1,295 @@
+use super::*;
+use crate::proto;
+
+/// Encodes a single counter via [MetricsEncoder::new_protobuf] and checks
+/// the resulting bytes against a `MetricFamily` message built by hand from
+/// the same `proto` primitives the encoder itself uses.
+#[test]
+fn protobuf_encodes_counter_as_length_delimited_metric_family() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_protobuf(&mut buf, 1_000);
+    encoder
+        .encode_counter("http_requests", 5.0, "Total requests")
+        .unwrap();
+    encoder.into_inner().unwrap();
+
+    let expected = proto::MetricFamily {
+        name: "http_requests".to_string(),
+        help: "Total requests".to_string(),
+        typ: proto::MetricType::Counter,
+        metrics: vec![proto::Metric {
+            labels: Vec::new(),
+            value: proto::MetricValue::Counter(5.0),
+            timestamp_ms: 1_000,
+        }],
+    }
+    .encode_length_delimited();
+
+    assert_eq!(buf, expected);
+}
+
+/// A [MetricsEncoder] can be driven through the object-safe [Encoder] trait,
+/// so [EncodeMetric] implementors don't need to know which wire format
+/// they're being scraped into.
+#[test]
+fn encoder_trait_object_drives_metrics_encoder() {
+    let mut buf = Vec::new();
+    let mut metrics_encoder = MetricsEncoder::new(&mut buf, 1_000);
+    let encoder: &mut dyn Encoder = &mut metrics_encoder;
+
+    encoder
+        .encode_labels(
+            "gauge",
+            "queue_depth",
+            "Items waiting",
+            &[(&[("queue", "emails")], 3.0), (&[("queue", "sms")], 0.0)],
+        )
+        .unwrap();
+
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.contains("queue_depth{queue=\"emails\"} 3 1000"));
+    assert!(text.contains("queue_depth{queue=\"sms\"} 0 1000"));
+}
+
+/// [MetricsEncoder::summary_vec] emits one sample per quantile plus the
+/// trailing `_sum`/`_count` lines, all labeled the same as the observation.
+#[test]
+fn summary_vec_encodes_quantiles_sum_and_count() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new(&mut buf, 1_000);
+    encoder
+        .summary_vec("request_latency_seconds", "Request latency")
+        .unwrap()
+        .observe(
+            &[("route", "/health")],
+            &[(0.5, 0.012), (0.99, 0.091)],
+            1.23,
+            42.0,
+        )
+        .unwrap();
+
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.contains("# TYPE request_latency_seconds summary"));
+    assert!(text.contains("request_latency_seconds{route=\"/health\",quantile=\"0.5\"} 0.012 1000"));
+    assert!(text.contains("request_latency_seconds{route=\"/health\",quantile=\"0.99\"} 0.091 1000"));
+    assert!(text.contains("request_latency_seconds_sum{route=\"/health\"} 1.23 1000"));
+    assert!(text.contains("request_latency_seconds_count{route=\"/health\"} 42 1000"));
+}
+
+/// In OpenMetrics mode, a counter's `# HELP`/`# TYPE` metadata must name the
+/// base metric (`http_requests`), while only the sample itself carries the
+/// `_total` suffix OpenMetrics requires; the output ends with `# EOF`.
+#[test]
+fn openmetrics_counter_keeps_base_name_in_metadata_and_suffixes_only_the_sample() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    encoder
+        .encode_counter("http_requests", 5.0, "Total requests")
+        .unwrap();
+    encoder.into_inner().unwrap();
+
+    let text = String::from_utf8(buf).unwrap();
+    assert_eq!(
+        text,
+        "# HELP http_requests Total requests\n\
+         # TYPE http_requests counter\n\
+         http_requests_total 5 1\n\
+         # EOF\n"
+    );
+}
+
+/// OpenMetrics sample timestamps are rendered in Unix-epoch seconds, as the
+/// spec requires; plain Prometheus text keeps the existing millisecond
+/// timestamps.
+#[test]
+fn openmetrics_timestamp_is_seconds_not_milliseconds() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_500);
+    encoder.encode_gauge("queue_depth", 3.0, "Items waiting").unwrap();
+    encoder.into_inner().unwrap();
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.contains("queue_depth 3 1.5\n"));
+
+    let mut ms_buf = Vec::new();
+    let mut ms_encoder = MetricsEncoder::new(&mut ms_buf, 1_500);
+    ms_encoder.encode_gauge("queue_depth", 3.0, "Items waiting").unwrap();
+    let ms_text = String::from_utf8(ms_buf).unwrap();
+    assert!(ms_text.contains("queue_depth 3 1500\n"));
+}
+
+/// An exemplar attached via [MetricsEncoder::encode_counter_with_exemplar]
+/// is only rendered in OpenMetrics mode, and is silently dropped in plain
+/// Prometheus text mode.
+#[test]
+fn exemplar_is_rendered_only_in_openmetrics_mode() {
+    let exemplar = || Exemplar {
+        labels: &[("trace_id", "abc123")],
+        value: 1.0,
+        timestamp: Some(1_234),
+    };
+
+    let mut openmetrics_buf = Vec::new();
+    let mut openmetrics_encoder = MetricsEncoder::new_openmetrics(&mut openmetrics_buf, 1_000);
+    openmetrics_encoder
+        .encode_counter_with_exemplar("requests", 1.0, "Requests", exemplar())
+        .unwrap();
+    let openmetrics_text = String::from_utf8(openmetrics_buf).unwrap();
+    assert!(openmetrics_text
+        .contains("requests_total 1 1 # {trace_id=\"abc123\"} 1 1234\n"));
+
+    let mut text_buf = Vec::new();
+    let mut text_encoder = MetricsEncoder::new(&mut text_buf, 1_000);
+    text_encoder
+        .encode_counter_with_exemplar("requests", 1.0, "Requests", exemplar())
+        .unwrap();
+    let text = String::from_utf8(text_buf).unwrap();
+    assert!(!text.contains("trace_id"));
+    assert!(text.contains("requests 1 1000\n"));
+}
+
+/// A bucket at `f64::INFINITY` is rendered as the `+Inf` bucket rather than
+/// as a second, redundant one, whether or not the caller already supplied
+/// it explicitly.
+#[test]
+fn histogram_with_infinity_bucket_does_not_duplicate_the_inf_bucket() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new(&mut buf, 1_000);
+    encoder
+        .encode_histogram(
+            "request_size_bytes",
+            [(1.0, 2.0), (f64::INFINITY, 1.0)].into_iter(),
+            3.0,
+            "Request sizes",
+        )
+        .unwrap();
+
+    let text = String::from_utf8(buf).unwrap();
+    assert_eq!(text.matches("le=\"+Inf\"").count(), 1);
+    assert!(text.contains("request_size_bytes_bucket{le=\"+Inf\"} 3 1000"));
+}
+
+/// `# UNIT` metadata must name the base metric, matching `# HELP`/`# TYPE`,
+/// not the `_total`-suffixed sample name — otherwise the unit no longer
+/// matches the declared suffix and OpenMetrics rejects the family.
+#[test]
+fn openmetrics_unit_metadata_uses_the_base_counter_name() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    encoder
+        .encode_counter_with_unit("request_seconds", 1.5, Unit::Seconds, "Request duration")
+        .unwrap();
+    encoder.into_inner().unwrap();
+
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.contains("# UNIT request_seconds seconds\n"));
+    assert!(text.contains("# HELP request_seconds Request duration\n"));
+    assert!(text.contains("# TYPE request_seconds counter\n"));
+    assert!(text.contains("request_seconds_total 1.5 1\n"));
+}
+
+/// A writer that always fails with a genuine I/O error, to distinguish it
+/// from the validation errors [crate::InvalidNameError] reports under the
+/// same [io::ErrorKind::InvalidInput].
+struct BadWriter;
+
+impl io::Write for BadWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "disk rejected write"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// In OpenMetrics mode, a counter name that already ends in `_total` is
+/// reported as an [io::Error] on the `try_*` path, not a panic, so a bad
+/// name sourced from runtime configuration can be skipped.
+#[test]
+fn try_encode_counter_reports_total_suffix_collision_as_an_error() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    let err = encoder
+        .try_encode_counter("requests_total", 1.0, "Requests")
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+/// The panicking counterpart still panics on the same input.
+#[test]
+#[should_panic]
+fn encode_counter_panics_on_total_suffix_collision() {
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    let _ = encoder.encode_counter("requests_total", 1.0, "Requests");
+}
+
+/// A genuine I/O error from the underlying writer is propagated as-is, and
+/// is not mistaken for a name-validation failure even though both are
+/// reported as [io::ErrorKind::InvalidInput].
+#[test]
+fn genuine_io_error_is_not_mistaken_for_a_validation_error() {
+    let mut encoder = MetricsEncoder::new(BadWriter, 1_000);
+    let err = encoder.encode_counter("requests", 1.0, "Requests").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "disk rejected write");
+}
+
+/// The 128 character exemplar limit covers the label names, values and the
+/// exemplar's own value, not the `k="v"` formatting punctuation
+/// [format_labels] adds around them, so many short labels that would only
+/// exceed the limit if punctuation were counted are accepted.
+#[test]
+fn try_render_exemplar_excludes_punctuation_from_the_character_limit() {
+    let labels: Vec<(&str, &str)> = (0..20).map(|_| ("k", "v")).collect();
+    let exemplar = Exemplar {
+        labels: &labels,
+        value: 1.0,
+        timestamp: None,
+    };
+    assert!(try_render_exemplar(&exemplar).is_ok());
+}
+
+/// An exemplar whose labels and value exceed the OpenMetrics 128 UTF-8
+/// character limit is reported as an [io::Error] on the `try_*` path, not a
+/// panic, so a scrape isn't aborted by a single oversized exemplar.
+#[test]
+fn try_histogram_with_exemplars_reports_oversized_exemplar_as_an_error() {
+    let long_value = "v".repeat(200);
+    let labels: &[(&str, &str)] = &[("trace_id", &long_value)];
+    let exemplar = Exemplar {
+        labels,
+        value: 1.0,
+        timestamp: None,
+    };
+
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    let err = encoder
+        .try_histogram_vec("request_size_bytes", "Request sizes")
+        .unwrap()
+        .try_histogram_with_exemplars(&[], [(1.0, 2.0, Some(exemplar))].into_iter(), 2.0)
+        .map(|_| ())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+/// The panicking counterpart still panics on the same oversized exemplar.
+#[test]
+#[should_panic]
+fn histogram_with_exemplars_panics_on_oversized_exemplar() {
+    let long_value = "v".repeat(200);
+    let labels: &[(&str, &str)] = &[("trace_id", &long_value)];
+    let exemplar = Exemplar {
+        labels,
+        value: 1.0,
+        timestamp: None,
+    };
+
+    let mut buf = Vec::new();
+    let mut encoder = MetricsEncoder::new_openmetrics(&mut buf, 1_000);
+    let _ = encoder
+        .histogram_vec("request_size_bytes", "Request sizes")
+        .unwrap()
+        .histogram_with_exemplars(&[], [(1.0, 2.0, Some(exemplar))].into_iter(), 2.0);
+}